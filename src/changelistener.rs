@@ -0,0 +1,174 @@
+use crate::desktopid::DesktopID;
+use crate::hresult::HRESULT;
+use crate::interfaces::{IApplicationView, IVirtualDesktop, IVirtualDesktopNotification, HWND};
+use crate::{get_desktop_names, get_desktops, get_index_by_desktop, Error};
+use com::ComRc;
+use crossbeam_channel::Sender;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::sync::Mutex;
+
+/// A virtual desktop lifecycle event, as reported by the shell's
+/// `IVirtualDesktopNotification` interface.
+///
+/// Desktops are identified by their index (resolved from the shell's internal
+/// `DesktopID` via `get_index_by_desktop`) rather than the raw ID, so
+/// consumers can match them up against `get_desktop_names`/`get_desktop_count`
+/// without going through the crate's internal types.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VirtualDesktopEvent {
+    /// The active desktop changed from one index to another
+    DesktopChanged(usize, usize),
+    /// A new desktop was created at `index`
+    DesktopCreated(usize),
+    /// The desktop at `index` was destroyed, its windows reparented onto `fallback`
+    DesktopDestroyed { index: usize, fallback: usize },
+    /// The desktop at `index` was renamed from `old` to `new`
+    DesktopRenamed { index: usize, old: String, new: String },
+    /// A desktop was reordered from one position to another
+    DesktopMoved { from: usize, to: usize },
+    /// A window's desktop membership changed
+    WindowChanged(HWND),
+}
+
+fn index_of(desktop: ComRc<dyn IVirtualDesktop>) -> Result<usize, Error> {
+    get_index_by_desktop(DesktopID::try_from(desktop)?)
+}
+
+fn seed_names() -> HashMap<DesktopID, String> {
+    match (get_desktops(), get_desktop_names()) {
+        (Ok(desktops), Ok(names)) => desktops.into_iter().zip(names).collect(),
+        _ => HashMap::new(),
+    }
+}
+
+/// Implements `IVirtualDesktopNotification`, translating the shell's callbacks
+/// into [`VirtualDesktopEvent`]s and forwarding them to `get_event_receiver`'s
+/// channel. Registered with the shell (and unregistered on drop) by
+/// `VirtualDesktopService`.
+pub(crate) struct VirtualDesktopChangeListener {
+    sender: Sender<VirtualDesktopEvent>,
+    // Desktop indices are only resolvable while the desktop still exists, so
+    // `virtual_desktop_destroy_begin` captures the index before the shell
+    // tears the desktop down; `virtual_desktop_destroyed` consumes it.
+    pending_destroy_index: Mutex<Option<usize>>,
+    // Keyed by the stable `DesktopID` rather than the index, since indices
+    // shift under creation/removal/reordering and would otherwise resolve
+    // renames to the wrong cached entry. Seeded eagerly so the first rename
+    // of a pre-existing desktop reports its real previous name instead of "".
+    names: Mutex<HashMap<DesktopID, String>>,
+}
+
+impl VirtualDesktopChangeListener {
+    pub(crate) fn new(sender: Sender<VirtualDesktopEvent>) -> Self {
+        VirtualDesktopChangeListener {
+            sender,
+            pending_destroy_index: Mutex::new(None),
+            names: Mutex::new(seed_names()),
+        }
+    }
+
+    fn send(&self, event: VirtualDesktopEvent) {
+        let _ = self.sender.send(event);
+    }
+}
+
+impl IVirtualDesktopNotification for VirtualDesktopChangeListener {
+    fn virtual_desktop_created(&self, desktop: ComRc<dyn IVirtualDesktop>) -> HRESULT {
+        if let Ok(id) = DesktopID::try_from(desktop) {
+            if let Ok(index) = get_index_by_desktop(id.clone()) {
+                if let Ok(names) = get_desktop_names() {
+                    if let Some(name) = names.get(index) {
+                        self.names.lock().unwrap().insert(id, name.clone());
+                    }
+                }
+                self.send(VirtualDesktopEvent::DesktopCreated(index));
+            }
+        }
+        HRESULT::ok()
+    }
+
+    fn virtual_desktop_destroy_begin(
+        &self,
+        desktop_destroyed: ComRc<dyn IVirtualDesktop>,
+        _desktop_fallback: ComRc<dyn IVirtualDesktop>,
+    ) -> HRESULT {
+        *self.pending_destroy_index.lock().unwrap() = index_of(desktop_destroyed).ok();
+        HRESULT::ok()
+    }
+
+    fn virtual_desktop_destroy_failed(
+        &self,
+        _desktop_destroyed: ComRc<dyn IVirtualDesktop>,
+        _desktop_fallback: ComRc<dyn IVirtualDesktop>,
+    ) -> HRESULT {
+        *self.pending_destroy_index.lock().unwrap() = None;
+        HRESULT::ok()
+    }
+
+    fn virtual_desktop_destroyed(
+        &self,
+        desktop_destroyed: ComRc<dyn IVirtualDesktop>,
+        desktop_fallback: ComRc<dyn IVirtualDesktop>,
+    ) -> HRESULT {
+        let index = self.pending_destroy_index.lock().unwrap().take();
+        if let Ok(id) = DesktopID::try_from(desktop_destroyed) {
+            self.names.lock().unwrap().remove(&id);
+        }
+        if let (Some(index), Ok(fallback)) = (index, index_of(desktop_fallback)) {
+            self.send(VirtualDesktopEvent::DesktopDestroyed { index, fallback });
+        }
+        HRESULT::ok()
+    }
+
+    fn virtual_desktop_moved(
+        &self,
+        _desktop: ComRc<dyn IVirtualDesktop>,
+        old_index: i64,
+        new_index: i64,
+    ) -> HRESULT {
+        self.send(VirtualDesktopEvent::DesktopMoved {
+            from: old_index as usize,
+            to: new_index as usize,
+        });
+        HRESULT::ok()
+    }
+
+    fn virtual_desktop_name_changed(
+        &self,
+        desktop: ComRc<dyn IVirtualDesktop>,
+        name: String,
+    ) -> HRESULT {
+        if let Ok(id) = DesktopID::try_from(desktop) {
+            if let Ok(index) = get_index_by_desktop(id.clone()) {
+                let mut names = self.names.lock().unwrap();
+                let old = names.insert(id, name.clone()).unwrap_or_default();
+                drop(names);
+                self.send(VirtualDesktopEvent::DesktopRenamed {
+                    index,
+                    old,
+                    new: name,
+                });
+            }
+        }
+        HRESULT::ok()
+    }
+
+    fn current_virtual_desktop_changed(
+        &self,
+        desktop_old: ComRc<dyn IVirtualDesktop>,
+        desktop_new: ComRc<dyn IVirtualDesktop>,
+    ) -> HRESULT {
+        if let (Ok(old), Ok(new)) = (index_of(desktop_old), index_of(desktop_new)) {
+            self.send(VirtualDesktopEvent::DesktopChanged(old, new));
+        }
+        HRESULT::ok()
+    }
+
+    fn view_virtual_desktop_changed(&self, view: ComRc<dyn IApplicationView>) -> HRESULT {
+        if let Ok(hwnd) = view.get_thumbnail_window() {
+            self.send(VirtualDesktopEvent::WindowChanged(hwnd));
+        }
+        HRESULT::ok()
+    }
+}
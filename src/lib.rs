@@ -18,6 +18,7 @@ use std::sync::{
     atomic::{AtomicBool, Ordering},
     Mutex,
 };
+use std::time::Duration;
 
 pub use crate::changelistener::VirtualDesktopEvent;
 pub use crate::error::Error;
@@ -32,18 +33,62 @@ static EVENTS: Lazy<(Sender<VirtualDesktopEvent>, Receiver<VirtualDesktopEvent>)
 
 static HAS_LISTENERS: AtomicBool = AtomicBool::new(false);
 
+/// Retry and settle behaviour around COM calls, see [`set_retry_policy`]
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// How many times to try obtaining (and, if needed, recreating) the service before giving up
+    pub max_attempts: u32,
+    /// Delay between failed attempts
+    pub backoff: Duration,
+    /// Delay after a state-changing call, to let the shell's COM state settle
+    pub settle: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 6,
+            backoff: Duration::from_millis(0),
+            settle: Duration::from_millis(0),
+        }
+    }
+}
+
+static RETRY_POLICY: Lazy<Mutex<RetryPolicy>> = Lazy::new(|| Mutex::new(RetryPolicy::default()));
+
+/// Set the retry/backoff/settle policy used by [`with_service`] and by mutating calls.
+///
+/// Defaults to 6 attempts with no delay, matching the historical hardcoded behavior.
+pub fn set_retry_policy(policy: RetryPolicy) {
+    if let Ok(mut current) = RETRY_POLICY.lock() {
+        *current = policy;
+    }
+}
+
+fn retry_policy() -> RetryPolicy {
+    RETRY_POLICY
+        .lock()
+        .map(|policy| *policy)
+        .unwrap_or_default()
+}
+
+fn settle() {
+    let policy = retry_policy();
+    if policy.settle > Duration::from_millis(0) {
+        std::thread::sleep(policy.settle);
+    }
+}
+
 fn error_side_effect(err: &Error) -> Result<bool, Error> {
     match err {
         Error::ComError(hresult) => {
             let comerror = ComError::from(*hresult);
 
-            #[cfg(feature = "debug")]
-            println!("ComError::{:?}", comerror);
+            log::trace!("ComError::{:?}", comerror);
 
             match comerror {
                 ComError::NotInitialized => {
-                    #[cfg(feature = "debug")]
-                    println!("Com initialize");
+                    log::debug!("Com initialize");
 
                     // This is the right initialization, it uses
                     // CoIncrementMTAUsage inside, and no CoInitialize function
@@ -57,8 +102,10 @@ fn error_side_effect(err: &Error) -> Result<bool, Error> {
 
                     Ok(true)
                 }
-                ComError::ClassNotRegistered => Ok(true),
-                ComError::RpcUnavailable => Ok(true),
+                ComError::ClassNotRegistered | ComError::RpcUnavailable => {
+                    log::warn!("{comerror:?}: recreating VirtualDesktopService (explorer.exe likely restarted)");
+                    Ok(true)
+                }
                 ComError::ObjectNotConnected => Ok(true),
                 ComError::Unknown(_) => Ok(false),
             }
@@ -72,9 +119,10 @@ fn with_service<T, F>(cb: F) -> Result<T, Error>
 where
     F: Fn(&VirtualDesktopService) -> Result<T, Error>,
 {
+    let policy = retry_policy();
     match SERVICE.lock() {
         Ok(cell) => {
-            for _ in 0..6 {
+            for attempt in 0..policy.max_attempts {
                 let service_ref: Ref<Result<Box<VirtualDesktopService>, Error>> = cell.borrow();
                 let result = service_ref.as_ref();
                 match result {
@@ -95,15 +143,16 @@ where
                     }
                 }
                 drop(service_ref);
-                #[cfg(feature = "debug")]
-                println!("Try to create");
+                if attempt + 1 < policy.max_attempts && policy.backoff > Duration::from_millis(0) {
+                    std::thread::sleep(policy.backoff);
+                }
+                log::debug!("Try to create");
                 let _ = cell.replace(VirtualDesktopService::create());
             }
             Err(Error::ServiceNotCreated)
         }
         Err(_) => {
-            #[cfg(feature = "debug")]
-            println!("Lock failed?");
+            log::warn!("Lock failed?");
             Err(Error::ServiceNotCreated)
         }
     }
@@ -172,17 +221,73 @@ pub fn is_window_on_desktop(hwnd: HWND, desktop: usize) -> Result<bool, Error> {
 
 /// Rename desktop
 pub fn rename_desktop(desktop: usize, name: &str) -> Result<(), Error> {
-    with_service(|s| s.rename_desktop(s.get_desktop_by_index(desktop)?, name))
+    let result = with_service(|s| s.rename_desktop(s.get_desktop_by_index(desktop)?, name));
+    if result.is_ok() {
+        settle();
+    }
+    result
 }
 
 /// Move window to desktop number
 pub fn move_window_to_desktop(hwnd: HWND, desktop: usize) -> Result<(), Error> {
-    with_service(|s| s.move_window_to_desktop(hwnd, &s.get_desktop_by_index(desktop)?))
+    let result = with_service(|s| s.move_window_to_desktop(hwnd, &s.get_desktop_by_index(desktop)?));
+    if result.is_ok() {
+        settle();
+    }
+    result
 }
 
 /// Go to desktop number
 pub fn go_to_desktop(desktop: usize) -> Result<(), Error> {
-    with_service(|s| s.go_to_desktop(&s.get_desktop_by_index(desktop)?))
+    let result = with_service(|s| s.go_to_desktop(&s.get_desktop_by_index(desktop)?));
+    if result.is_ok() {
+        settle();
+    }
+    result
+}
+
+/// Compute the desktop index `offset` positions away from `current`, out of `count` desktops.
+/// Clamps to `[0, count)` when `wrap` is false, wraps around (`rem_euclid`) when `wrap` is true.
+/// Returns `None` only when `count` is zero.
+fn wrap_index(current: usize, count: usize, offset: isize, wrap: bool) -> Option<usize> {
+    if count == 0 {
+        return None;
+    }
+
+    let count = count as isize;
+    let target = current as isize + offset;
+
+    if wrap {
+        Some(target.rem_euclid(count) as usize)
+    } else {
+        Some(target.clamp(0, count - 1) as usize)
+    }
+}
+
+fn relative_desktop_index(offset: isize, wrap: bool) -> Result<usize, Error> {
+    let current = get_current_desktop()?;
+    let count = get_desktop_count()? as usize;
+    wrap_index(current, count, offset, wrap).ok_or(Error::DesktopNotFound)
+}
+
+/// Go to the desktop `offset` positions away from the current one, wrapping around when `wrap` is true
+pub fn go_to_desktop_relative(offset: isize, wrap: bool) -> Result<(), Error> {
+    go_to_desktop(relative_desktop_index(offset, wrap)?)
+}
+
+/// Move a window to the desktop `offset` positions away from the current one, wrapping around when `wrap` is true
+pub fn move_window_to_desktop_relative(hwnd: HWND, offset: isize, wrap: bool) -> Result<(), Error> {
+    move_window_to_desktop(hwnd, relative_desktop_index(offset, wrap)?)
+}
+
+/// Go to the next desktop, wrapping around after the last one
+pub fn go_to_next_desktop() -> Result<(), Error> {
+    go_to_desktop_relative(1, true)
+}
+
+/// Go to the previous desktop, wrapping around before the first one
+pub fn go_to_previous_desktop() -> Result<(), Error> {
+    go_to_desktop_relative(-1, true)
 }
 
 /// Is window pinned?
@@ -200,6 +305,104 @@ pub fn unpin_window(hwnd: HWND) -> Result<(), Error> {
     with_service(|s| s.unpin_window(hwnd))
 }
 
+/// Is the application `hwnd` belongs to pinned on all desktops?
+pub fn is_app_pinned(hwnd: HWND) -> Result<bool, Error> {
+    with_service(|s| s.is_app_pinned(hwnd))
+}
+
+/// Pin the application `hwnd` belongs to, so all of its windows appear on every desktop
+pub fn pin_app(hwnd: HWND) -> Result<(), Error> {
+    with_service(|s| s.pin_app(hwnd))
+}
+
+/// Unpin the application `hwnd` belongs to
+pub fn unpin_app(hwnd: HWND) -> Result<(), Error> {
+    with_service(|s| s.unpin_app(hwnd))
+}
+
+/// Create a new desktop, returns the index of the created desktop
+pub fn create_desktop() -> Result<usize, Error> {
+    let result = with_service(|s| {
+        let desktop = s.create_desktop()?;
+        s.get_index_by_desktop(desktop)
+    });
+    if result.is_ok() {
+        settle();
+    }
+    result
+}
+
+/// Remove desktop, moving any of its windows onto the desktop at `fallback`
+pub fn remove_desktop(desktop: usize, fallback: usize) -> Result<(), Error> {
+    let result = with_service(|s| {
+        let desktop = s.get_desktop_by_index(desktop)?;
+        let fallback = s.get_desktop_by_index(fallback)?;
+        s.remove_desktop(desktop, fallback)
+    });
+    if result.is_ok() {
+        settle();
+    }
+    result
+}
+
+/// Move desktop at `from` to the position `to`, reordering the desktops in between
+pub fn move_desktop(from: usize, to: usize) -> Result<(), Error> {
+    let result = with_service(|s| {
+        let desktop = s.get_desktop_by_index(from)?;
+        s.move_desktop(desktop, to)
+    });
+    if result.is_ok() {
+        settle();
+    }
+    result
+}
+
+/// How [`summon_window`] should bring a window into view
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SummonMode {
+    /// Move the window to the current desktop and focus it
+    ToCurrent,
+    /// Leave the window where it is and switch to the desktop it lives on
+    Any,
+    /// Focus the window only if it is already on the current desktop, otherwise do nothing
+    OnCurrent,
+}
+
+fn focus_window(hwnd: HWND) {
+    unsafe {
+        winapi::um::winuser::SetForegroundWindow(hwnd as *mut _);
+    }
+}
+
+/// Bring a window into view according to `mode`, handling the move/switch and focus in one call
+pub fn summon_window(hwnd: HWND, mode: SummonMode) -> Result<(), Error> {
+    match mode {
+        SummonMode::ToCurrent => {
+            let current = get_current_desktop()?;
+            move_window_to_desktop(hwnd, current)?;
+            if get_desktop_by_window(hwnd)? != current {
+                return Err(Error::DesktopNotFound);
+            }
+            focus_window(hwnd);
+            Ok(())
+        }
+        SummonMode::Any => {
+            let target = get_desktop_by_window(hwnd)?;
+            go_to_desktop(target)?;
+            if get_current_desktop()? != target {
+                return Err(Error::DesktopNotFound);
+            }
+            Ok(())
+        }
+        SummonMode::OnCurrent => {
+            if is_window_on_current_virtual_desktop(hwnd)? {
+                focus_window(hwnd);
+            }
+            Ok(())
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -239,28 +442,37 @@ mod tests {
     #[test]
     fn test_desktop_moves() {
         sync_test(|| {
+            set_retry_policy(RetryPolicy {
+                settle: Duration::from_millis(400),
+                ..RetryPolicy::default()
+            });
+
             let current_desktop = get_current_desktop().unwrap();
 
             // Go to desktop 0, ensure it worked
             go_to_desktop(0).unwrap();
             assert_eq!(get_current_desktop().unwrap(), 0);
-            std::thread::sleep(Duration::from_millis(400));
 
             // Go to desktop 1, ensure it worked
             go_to_desktop(1).unwrap();
             assert_eq!(get_current_desktop().unwrap(), 1);
-            std::thread::sleep(Duration::from_millis(400));
 
             // Go to desktop where it was, ensure it worked
             go_to_desktop(current_desktop).unwrap();
             assert_eq!(get_current_desktop().unwrap(), current_desktop);
-            std::thread::sleep(Duration::from_millis(400));
+
+            set_retry_policy(RetryPolicy::default());
         })
     }
 
     #[test]
     fn test_move_notepad_between_desktops() {
         sync_test(|| {
+            set_retry_policy(RetryPolicy {
+                settle: Duration::from_millis(300),
+                ..RetryPolicy::default()
+            });
+
             // Get notepad
             let notepad_hwnd: HWND = unsafe {
                 let notepad = "notepad\0".encode_utf16().collect::<Vec<_>>();
@@ -289,12 +501,10 @@ mod tests {
             move_window_to_desktop(notepad_hwnd, 0).unwrap();
             let notepad_desktop = get_desktop_by_window(notepad_hwnd).unwrap();
             assert_eq!(notepad_desktop, 0, "Notepad should have moved to desktop 0");
-            std::thread::sleep(Duration::from_millis(300));
 
             move_window_to_desktop(notepad_hwnd, 1).unwrap();
             let notepad_desktop = get_desktop_by_window(notepad_hwnd).unwrap();
             assert_eq!(notepad_desktop, 1, "Notepad should have moved to desktop 1");
-            std::thread::sleep(Duration::from_millis(300));
 
             move_window_to_desktop(notepad_hwnd, current_desktop).unwrap();
             let notepad_desktop = get_desktop_by_window(notepad_hwnd).unwrap();
@@ -302,12 +512,19 @@ mod tests {
                 notepad_desktop, current_desktop,
                 "Notepad should have moved to desktop 0"
             );
+
+            set_retry_policy(RetryPolicy::default());
         })
     }
 
     #[test]
     fn test_pin_notepad() {
         sync_test(|| {
+            set_retry_policy(RetryPolicy {
+                settle: Duration::from_millis(1000),
+                ..RetryPolicy::default()
+            });
+
             // Get notepad
             let notepad_hwnd: HWND = unsafe {
                 let notepad = "notepad\0".encode_utf16().collect::<Vec<_>>();
@@ -336,7 +553,6 @@ mod tests {
             go_to_desktop(0).unwrap();
 
             assert_eq!(is_pinned_window(notepad_hwnd).unwrap(), true);
-            std::thread::sleep(Duration::from_millis(1000));
 
             go_to_desktop(current_desktop).unwrap();
             unpin_window(notepad_hwnd).unwrap();
@@ -344,7 +560,8 @@ mod tests {
                 is_window_on_desktop(notepad_hwnd, current_desktop).unwrap(),
                 true
             );
-            std::thread::sleep(Duration::from_millis(1000));
+
+            set_retry_policy(RetryPolicy::default());
         })
     }
 
@@ -385,4 +602,28 @@ mod tests {
         let err = move_window_to_desktop(999999, 0).unwrap_err();
         assert_eq!(err, Error::WindowNotFound);
     }
+
+    /// Pure index math behind go_to_desktop_relative/move_window_to_desktop_relative
+    #[test]
+    fn test_wrap_index() {
+        assert_eq!(wrap_index(0, 4, 1, false), Some(1));
+        assert_eq!(wrap_index(0, 4, 0, false), Some(0));
+        assert_eq!(wrap_index(3, 4, 1, false), Some(3), "clamped, does not wrap");
+        assert_eq!(wrap_index(0, 4, -1, false), Some(0), "clamped, does not wrap");
+
+        assert_eq!(wrap_index(3, 4, 1, true), Some(0), "wraps past the last desktop");
+        assert_eq!(wrap_index(0, 4, -1, true), Some(3), "wraps before the first desktop");
+        assert_eq!(wrap_index(0, 4, -5, true), Some(3), "wraps across more than one lap");
+
+        assert_eq!(wrap_index(0, 0, 0, false), None, "no desktops, no valid index");
+        assert_eq!(wrap_index(0, 0, 1, true), None, "must not divide by zero");
+    }
+
+    #[test]
+    fn test_retry_policy_default() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.max_attempts, 6);
+        assert_eq!(policy.backoff, Duration::from_millis(0));
+        assert_eq!(policy.settle, Duration::from_millis(0));
+    }
 }